@@ -1,39 +1,85 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     fmt, mem, str,
     sync::atomic::{self, AtomicUsize, Ordering},
 };
 
-use owning_ref::OwningHandle;
-use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use parking_lot::{RwLock, RwLockWriteGuard};
 
 pub(crate) use tracing_core::span::{Attributes, Current, Id, Record};
 use tracing_core::{dispatcher, Metadata};
 
-pub struct Span<'a> {
-    lock: OwningHandle<RwLockReadGuard<'a, Slab>, RwLockReadGuard<'a, Slot>>,
+/// A fixed-identity source of span slots that a `Span` can reclaim a slot
+/// through once it's the last view into it.
+///
+/// This is the common ground between the sharded, page-growable [`Store`]
+/// and the fixed-capacity [`StaticStore`]: both hand out slot pointers from
+/// some backing allocation (a page table vs. an inline array) and both need
+/// to push a closed, view-free slot back onto a free list, but *how* they do
+/// either of those things differs. Spelling that difference out as a trait
+/// lets `Span` stay agnostic to which kind of store it came from.
+trait SlotSource<B> {
+    /// Reclaims the slot at `idx` onto the free list, if it is actually
+    /// eligible (see `Slot::is_reclaimable`).
+    fn reclaim(&self, idx: usize) -> Option<Data>
+    where
+        B: Clear;
+}
+
+/// A view of a single span's data.
+///
+/// Unlike the slab/slot locks it reads through, a `Span` does not hold
+/// either for its whole lifetime --- it only takes the slot's lock for the
+/// instant it takes to read a single field. This means a `Span` can safely
+/// be held across a `.await` point or a long-running format operation
+/// without blocking `record` calls on the same span, or blocking the slab
+/// from reclaiming the slot once the span closes. Reclamation of a slot
+/// that closes while `Span`s still point into it is deferred until the
+/// last of them is dropped (see `Slot`'s lifecycle fields).
+pub struct Span<'a, B = String, S = Shard<B>> {
+    source: &'a S,
+    ptr: *const RwLock<Slot<B>>,
+    idx: usize,
+    generation: usize,
 }
 
 /// Represents the `Subscriber`'s view of the current span context to a
 /// formatter.
 #[derive(Debug)]
-pub struct Context<'a, N> {
-    store: &'a Store,
+pub struct Context<'a, N, B = String> {
+    store: &'a Store<B>,
     new_visitor: &'a N,
 }
 
 /// Stores data associated with currently-active spans.
+///
+/// Span data is sharded across a number of independent slabs, one per CPU.
+/// Each thread is assigned (and caches) one shard, so that spans created by
+/// different threads almost never contend with one another: allocating a new
+/// span only ever takes the read lock on the calling thread's own shard, and
+/// growing a shard only blocks threads that share that particular shard
+/// rather than every thread in the process.
+#[derive(Debug)]
+pub(crate) struct Store<B = String> {
+    shards: Box<[Shard<B>]>,
+
+    // Used to assign each new thread a shard, round-robin, the first time it
+    // creates a span.
+    next_shard: AtomicUsize,
+}
+
 #[derive(Debug)]
-pub(crate) struct Store {
+struct Shard<B = String> {
     // Active span data is stored in a slab of span slots. Each slot has its own
     // read-write lock to guard against concurrent modification to its data.
     // Thus, we can modify any individual slot by acquiring a read lock on the
     // slab, and using that lock to acquire a write lock on the slot we wish to
-    // modify. It is only necessary to acquire the write lock here when the
-    // slab itself has to be modified (i.e., to allocate more slots).
-    inner: RwLock<Slab>,
+    // modify. The slab itself is a page table (see `Slab`/`Page`), so growing
+    // it never requires taking a lock any wider than the single page being
+    // allocated.
+    inner: RwLock<Slab<B>>,
 
-    // The head of the slab's "free list".
+    // The head of the shard's "free list".
     next: AtomicUsize,
 }
 
@@ -45,14 +91,109 @@ pub(crate) struct Data {
     is_empty: bool,
 }
 
-#[derive(Debug)]
-struct Slab {
-    slab: Vec<RwLock<Slot>>,
+// The size, in slots, of the first page. Each subsequent page doubles the
+// size of the one before it.
+const INITIAL_PAGE_SIZE: usize = 32;
+const INITIAL_PAGE_SIZE_BITS: usize = 5; // log2(INITIAL_PAGE_SIZE)
+
+// The number of pages a slab may grow to. 20 pages is enough to hold
+// `INITIAL_PAGE_SIZE * (2^20 - 1)` slots --- far more than any process will
+// realistically need --- while staying small enough for a fixed-size array.
+const PAGE_COUNT: usize = 20;
+
+/// A table of lazily-allocated pages of span slots.
+///
+/// Unlike a `Vec`, growing a `Slab` never reallocates or moves existing
+/// slots: each page is boxed independently and, once allocated, is never
+/// moved or freed until the `Slab` itself is dropped. This means a
+/// reference into a page remains valid for as long as the `Slab` does, even
+/// while later pages are still being allocated.
+#[derive(Debug, Default)]
+struct Slab<B = String> {
+    pages: [Page<B>; PAGE_COUNT],
+}
+
+#[derive(Debug, Default)]
+struct Page<B = String> {
+    // `None` until this page is first needed, at which point it is
+    // allocated once and never replaced. Readers only need to hold this
+    // lock for the instant it takes to check whether the page exists (or,
+    // the first time, to allocate it); they do not hold it while reading or
+    // writing an individual slot.
+    slots: RwLock<Option<Box<[RwLock<Slot<B>>]>>>,
+}
+
+/// Returns the first global slot index stored on the given page.
+#[inline]
+fn page_start(page: usize) -> usize {
+    INITIAL_PAGE_SIZE * ((1 << page) - 1)
+}
+
+/// Returns the number of slots stored on the given page.
+#[inline]
+fn page_len(page: usize) -> usize {
+    INITIAL_PAGE_SIZE << page
+}
+
+/// Splits a global, linear slot index into the page that owns it and the
+/// slot's offset within that page.
+#[inline]
+fn page_of(idx: usize) -> (usize, usize) {
+    let shifted = idx + INITIAL_PAGE_SIZE;
+    // The index of the highest set bit, found via `usize::BITS` rather than
+    // a hardcoded `63` so this doesn't silently break on 32-bit targets
+    // (where a `usize`'s `leading_zeros()` is relative to a 32-, not
+    // 64-bit, width).
+    let highest_bit = usize::BITS as usize - 1 - shifted.leading_zeros() as usize;
+    let page = highest_bit - INITIAL_PAGE_SIZE_BITS;
+    (page, idx - page_start(page))
+}
+
+// The `lifecycle` bit layout: bit 0 is the "marked" flag, set once the span
+// has fully closed; the remaining bits count outstanding `Span` views into
+// the slot.
+const MARKED: usize = 0b1;
+const REF_ONE: usize = 0b10;
+
+/// Resets a recycled value to its empty state while retaining whatever
+/// capacity it has already allocated.
+///
+/// A slot's field buffer is cleared through this trait, rather than by
+/// simply dropping and replacing it, so that the heap allocation backing it
+/// (e.g. a `String`'s buffer) is kept around for the next span to reuse
+/// instead of being freed and reallocated from scratch. `Slot`'s `B` is
+/// specifically that field buffer, not a general extension-data slot ---
+/// anything recorded about a span beyond its formatted fields still lives
+/// outside the slab --- but any type that formats fields into itself and
+/// implements `Clear + Default` (like [`ArrayString`]) can stand in for the
+/// default `String` and get the same pooling.
+pub(crate) trait Clear {
+    /// Resets `self` to its empty state, retaining any allocated capacity.
+    fn clear(&mut self);
+}
+
+impl Clear for String {
+    fn clear(&mut self) {
+        String::clear(self)
+    }
 }
 
 #[derive(Debug)]
-struct Slot {
-    fields: String,
+struct Slot<B = String> {
+    fields: B,
+    // Bumped every time this slot is emptied, so that stale `Id`s minted
+    // before the slot was recycled can be detected and rejected rather than
+    // silently resolving to whatever span now occupies the slot.
+    generation: usize,
+    // Tracks this slot's place in the `Present` -> `Marked` -> `Removing`
+    // lifecycle: a slot starts out `Present` (lifecycle == 0); once the span
+    // closes it becomes `Marked` (the `MARKED` bit is set), but it is only
+    // actually emptied ("Removing", in `Slab::remove`) once `Marked` *and*
+    // no outstanding `Span` views remain. Packing both into one atomic lets
+    // whichever of `drop_span` or the last `Span::drop` observes that
+    // condition perform the reclamation, without the two ever racing to
+    // decide who's responsible.
+    lifecycle: AtomicUsize,
     span: State,
 }
 
@@ -64,6 +205,68 @@ enum State {
 
 thread_local! {
     static CONTEXT: RefCell<Vec<Id>> = RefCell::new(vec![]);
+
+    // The shard assigned to this thread, cached after the first span is
+    // created on it.
+    static THREAD_SHARD: Cell<Option<usize>> = Cell::new(None);
+}
+
+// A span `Id` packs three values into a single `u64`, from high bits to low:
+// the shard index, the slot's generation, and the slot's index within the
+// shard. The generation is folded in so that an `Id` minted for a slot
+// before it was recycled can never be mistaken for the slot's current
+// occupant (see `idx_to_id`/`id_to_idx`).
+const SHARD_BITS: u64 = 8;
+const GENERATION_BITS: u64 = 16;
+const SHARD_SHIFT: u64 = 64 - SHARD_BITS;
+const GENERATION_SHIFT: u64 = SHARD_SHIFT - GENERATION_BITS;
+const MAX_IDX: u64 = (1 << GENERATION_SHIFT) - 1;
+const MAX_GENERATION: usize = (1 << GENERATION_BITS) - 1;
+
+// The last step at which `Backoff::spin` still spins rather than yielding:
+// steps 0 through `BACKOFF_SPIN_LIMIT` spin (doubling the spin count each
+// time), and every call after that yields the thread instead.
+const BACKOFF_SPIN_LIMIT: u32 = 6;
+
+/// Backs off a lock-free retry loop under contention.
+///
+/// Retrying a failed CAS with a bare `spin_loop_hint` wastes cycles hammering
+/// the same cache line as fast as the CPU will issue the instruction. This
+/// spins an exponentially increasing number of times on the first few
+/// retries, and once that stops being worth it, falls back to yielding the
+/// thread to the scheduler so other threads sharing the core get a chance to
+/// make progress.
+struct Backoff {
+    step: u32,
+}
+
+impl Backoff {
+    fn new() -> Self {
+        Self { step: 0 }
+    }
+
+    /// Backs off once, spinning a little longer than the last call (up to a
+    /// point) before it starts yielding the thread instead.
+    fn spin(&mut self) {
+        if self.step <= BACKOFF_SPIN_LIMIT {
+            for _ in 0..(1 << self.step) {
+                atomic::spin_loop_hint();
+            }
+            self.step += 1;
+        } else {
+            std::thread::yield_now();
+        }
+    }
+
+    /// Resets the spin count back to its starting point.
+    ///
+    /// Called once a retry loop has actually taken a fresh, uncontended
+    /// snapshot to work from (e.g. `try_write` succeeding), rather than
+    /// carrying an escalated spin count forward from an unrelated,
+    /// already-resolved bout of contention on an earlier snapshot.
+    fn reset(&mut self) {
+        self.step = 0;
+    }
 }
 
 macro_rules! debug_panic {
@@ -78,47 +281,55 @@ macro_rules! debug_panic {
 
 // ===== impl Span =====
 
-impl<'a> Span<'a> {
-    pub fn name(&self) -> &'static str {
-        match self.lock.span {
-            State::Full(ref data) => data.metadata.name(),
-            State::Empty(_) => unreachable!(),
+impl<'a, B, S> Span<'a, B, S> {
+    /// Takes the slot's read lock just long enough to run `f` against its
+    /// data, then releases it. A `Span` never holds this lock between
+    /// calls.
+    #[inline]
+    fn with_data<T>(&self, f: impl FnOnce(&Data) -> T) -> T {
+        let slot = unsafe { &*self.ptr }.read();
+        match slot.span {
+            State::Full(ref data) => f(data),
+            State::Empty(_) => unreachable!("a held Span's slot cannot be emptied"),
         }
     }
 
+    pub fn name(&self) -> &'static str {
+        self.with_data(|data| data.metadata.name())
+    }
+
     pub fn metadata(&self) -> &'static Metadata<'static> {
-        match self.lock.span {
-            State::Full(ref data) => data.metadata,
-            State::Empty(_) => unreachable!(),
-        }
+        self.with_data(|data| data.metadata)
     }
 
-    pub fn fields(&self) -> &str {
-        self.lock.fields.as_ref()
+    pub fn fields(&self) -> B
+    where
+        B: Clone,
+    {
+        unsafe { &*self.ptr }.read().fields.clone()
     }
 
-    pub fn parent(&self) -> Option<&Id> {
-        match self.lock.span {
-            State::Full(ref data) => data.parent.as_ref(),
-            State::Empty(_) => unreachable!(),
-        }
+    pub fn parent(&self) -> Option<Id> {
+        self.with_data(|data| data.parent.clone())
     }
+}
 
+impl<'a, B> Span<'a, B> {
     #[inline(always)]
     fn with_parent<'store, F, E>(
         self,
         my_id: &Id,
         last_id: Option<&Id>,
         f: &mut F,
-        store: &'store Store,
+        store: &'store Store<B>,
     ) -> Result<(), E>
     where
-        F: FnMut(&Id, Span<'_>) -> Result<(), E>,
+        F: FnMut(&Id, Span<'_, B>) -> Result<(), E>,
     {
         if let Some(parent_id) = self.parent() {
-            if Some(parent_id) != last_id {
-                if let Some(parent) = store.get(parent_id) {
-                    parent.with_parent(parent_id, Some(my_id), f, store)?;
+            if Some(&parent_id) != last_id {
+                if let Some(parent) = store.get(&parent_id) {
+                    parent.with_parent(&parent_id, Some(my_id), f, store)?;
                 } else {
                     debug_panic!("missing span for {:?}; this is a bug", parent_id);
                 }
@@ -128,7 +339,28 @@ impl<'a> Span<'a> {
     }
 }
 
-impl<'a> fmt::Debug for Span<'a> {
+impl<'a, B, S> Drop for Span<'a, B, S>
+where
+    B: Clear,
+    S: SlotSource<B>,
+{
+    fn drop(&mut self) {
+        let slot = unsafe { &*self.ptr };
+        // If this looks like it was the last view into a slot whose span
+        // had already closed, attempt to reclaim it. The `SlotSource`
+        // re-validates that under the slot's write lock, so it's always
+        // safe to call speculatively; it's a no-op if we're wrong (the
+        // span hasn't closed yet, or another view is still outstanding).
+        if slot.release_ref() {
+            let _ = self.source.reclaim(self.idx);
+        }
+    }
+}
+
+impl<'a, B, S> fmt::Debug for Span<'a, B, S>
+where
+    B: Clone + fmt::Debug,
+{
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Span")
             .field("name", &self.name())
@@ -139,9 +371,47 @@ impl<'a> fmt::Debug for Span<'a> {
     }
 }
 
+/// The operations common to every kind of span store --- the growable,
+/// sharded [`Store`] and the fixed-capacity [`StaticStore`] alike.
+///
+/// `new_span` is deliberately not part of this trait: a growable store
+/// almost always succeeds by allocating more room (it only runs out once
+/// its slab hits the hard cap described on [`PAGE_COUNT`], which takes tens
+/// of millions of simultaneously-live spans), while a fixed-capacity store
+/// can run out far sooner, at its configured size --- different enough
+/// growth semantics that each is better off spelling out its own
+/// `new_span` rather than sharing one through this trait.
+pub(crate) trait SpanStore<B = String> {
+    /// The kind of [`SlotSource`] this store hands out `Span`s pointing
+    /// into.
+    type Source: SlotSource<B>;
+
+    /// Returns a `Span` to the span with the specified `id`, if one
+    /// currently exists.
+    fn get(&self, id: &Id) -> Option<Span<'_, B, Self::Source>>;
+
+    /// Records that the span with the given `id` has the given `fields`.
+    fn record<N>(&self, id: &Id, fields: &Record<'_>, new_recorder: &N)
+    where
+        N: for<'a> super::NewVisitor<'a>,
+        B: fmt::Write + AsRef<str>;
+
+    /// Decrements the reference count of the span with the given `id`, and
+    /// removes the span if it is zero.
+    fn drop_span(&self, id: Id) -> bool
+    where
+        B: Clear;
+
+    /// Clones the span with the given `id`, returning a new `Id` for it.
+    fn clone_span(&self, id: &Id) -> Id;
+}
+
 // ===== impl Context =====
 
-impl<'a, N> Context<'a, N> {
+impl<'a, N, B> Context<'a, N, B>
+where
+    B: Clear,
+{
     /// Applies a function to each span in the current trace context.
     ///
     /// The function is applied in order, beginning with the root of the trace,
@@ -154,7 +424,7 @@ impl<'a, N> Context<'a, N> {
     /// than potentially causing a double panic.
     pub fn visit_spans<F, E>(&self, mut f: F) -> Result<(), E>
     where
-        F: FnMut(&Id, Span<'_>) -> Result<(), E>,
+        F: FnMut(&Id, Span<'_, B>) -> Result<(), E>,
     {
         CONTEXT
             .try_with(|current| {
@@ -176,7 +446,7 @@ impl<'a, N> Context<'a, N> {
     /// Executes a closure with the reference to the current span.
     pub fn with_current<F, R>(&self, f: F) -> Option<R>
     where
-        F: FnOnce((&Id, Span<'_>)) -> R,
+        F: FnOnce((&Id, Span<'_, B>)) -> R,
     {
         // If the lock is poisoned or the thread local has already been
         // destroyed, we might be in the middle of unwinding, so this
@@ -195,7 +465,19 @@ impl<'a, N> Context<'a, N> {
             .ok()?
     }
 
-    pub(crate) fn new(store: &'a Store, new_visitor: &'a N) -> Self {
+    /// Returns an iterator over every span currently open anywhere in the
+    /// process, in no particular order.
+    ///
+    /// Unlike [`visit_spans`](Self::visit_spans) and
+    /// [`with_current`](Self::with_current), which only see the calling
+    /// thread's active span and its ancestors, this enumerates every span
+    /// still open on every thread --- the basis for a "list all open spans"
+    /// diagnostic command.
+    pub fn unique_spans(&self) -> impl Iterator<Item = Span<'_, B>> + '_ {
+        self.store.unique_iter()
+    }
+
+    pub(crate) fn new(store: &'a Store<B>, new_visitor: &'a N) -> Self {
         Self { store, new_visitor }
     }
 
@@ -213,26 +495,92 @@ impl<'a, N> Context<'a, N> {
     }
 }
 
+/// Packs a shard index, a slot's generation, and the slot's index within
+/// that shard into a single span `Id`, mirroring the layout documented on
+/// [`SHARD_BITS`].
 #[inline]
-fn idx_to_id(idx: usize) -> Id {
-    Id::from_u64(idx as u64 + 1)
+fn idx_to_id(shard: usize, idx: usize, generation: usize) -> Id {
+    Id::from_u64(
+        ((shard as u64) << SHARD_SHIFT)
+            | ((generation as u64) << GENERATION_SHIFT)
+            | (idx as u64 + 1),
+    )
 }
 
+/// The inverse of [`idx_to_id`]: splits a span `Id` back into the shard
+/// index, the slot's generation, and the slot's index within that shard.
 #[inline]
-fn id_to_idx(id: &Id) -> usize {
-    id.into_u64() as usize - 1
+fn id_to_idx(id: &Id) -> (usize, usize, usize) {
+    let id = id.into_u64();
+    let shard = (id >> SHARD_SHIFT) as usize;
+    let generation = ((id >> GENERATION_SHIFT) & MAX_GENERATION as u64) as usize;
+    let idx = (id & MAX_IDX) as usize - 1;
+    (shard, idx, generation)
+}
+
+/// Returns the number of shards the `Store` should be divided into ---
+/// one per available CPU, so that threads almost never contend with one
+/// another when allocating spans.
+///
+/// This is capped at `1 << SHARD_BITS`: a shard index has to fit in the
+/// bits `idx_to_id` reserves for it, so on a machine with more logical
+/// CPUs than that, threads are simply spread across fewer shards than
+/// there are CPUs rather than minting a shard index that would overflow
+/// into the generation bits.
+fn num_shards() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(1 << SHARD_BITS)
 }
 
-impl Store {
-    pub(crate) fn with_capacity(capacity: usize) -> Self {
+impl<B> Store<B> {
+    pub(crate) fn with_capacity(capacity: usize) -> Self
+    where
+        B: Default,
+    {
+        let shards = (0..num_shards())
+            .map(|_| {
+                let slab = Slab::default();
+                if capacity > 0 {
+                    // Eagerly allocate the first page (`INITIAL_PAGE_SIZE`
+                    // slots, whatever `capacity` is) rather than waiting for
+                    // the first span to be created on this shard. Page 0 always
+                    // exists (`PAGE_COUNT` is never 0), so this never fails.
+                    let _ = slab.get_or_alloc_page(0);
+                }
+                Shard {
+                    inner: RwLock::new(slab),
+                    next: AtomicUsize::new(0),
+                }
+            })
+            .collect();
         Store {
-            inner: RwLock::new(Slab {
-                slab: Vec::with_capacity(capacity),
-            }),
-            next: AtomicUsize::new(0),
+            shards,
+            next_shard: AtomicUsize::new(0),
         }
     }
 
+    /// Returns the shard assigned to the current thread, assigning one
+    /// (round-robin) and caching it in a thread-local the first time this is
+    /// called on a given thread.
+    #[inline]
+    fn current_shard(&self) -> usize {
+        THREAD_SHARD.with(|cell| {
+            if let Some(shard) = cell.get() {
+                // `THREAD_SHARD` is a single process-global cache shared by
+                // every `Store` a thread touches, so a shard cached against
+                // one `Store` isn't necessarily in range for another with a
+                // different `shards.len()`; re-reduce it against *this*
+                // store rather than trusting it verbatim.
+                return shard % self.shards.len();
+            }
+            let shard = self.next_shard.fetch_add(1, Ordering::Relaxed) % self.shards.len();
+            cell.set(Some(shard));
+            shard
+        })
+    }
+
     #[inline]
     pub(crate) fn current(&self) -> Option<Id> {
         CONTEXT
@@ -251,7 +599,10 @@ impl Store {
         });
     }
 
-    pub(crate) fn pop(&self, expected_id: &Id) {
+    pub(crate) fn pop(&self, expected_id: &Id)
+    where
+        B: Clear,
+    {
         let id = CONTEXT
             .try_with(|current| {
                 let mut current = current.borrow_mut();
@@ -269,85 +620,105 @@ impl Store {
     }
 
     /// Inserts a new span with the given data and fields into the slab,
-    /// returning an ID for that span.
+    /// returning an ID for that span, or `None` if the shard's slab has hit
+    /// its hard capacity (see [`PAGE_COUNT`]) and cannot grow any further.
     ///
     /// If there are empty slots in the slab previously allocated for spans
     /// which have since been closed, the allocation and span ID of the most
     /// recently emptied span will be reused. Otherwise, a new allocation will
     /// be added to the slab.
     #[inline]
-    pub(crate) fn new_span<N>(&self, attrs: &Attributes<'_>, new_visitor: &N) -> Id
+    pub(crate) fn new_span<N>(&self, attrs: &Attributes<'_>, new_visitor: &N) -> Option<Id>
     where
         N: for<'a> super::NewVisitor<'a>,
+        B: Default + fmt::Write + AsRef<str>,
     {
         let mut span = Some(Data::new(attrs, self));
 
-        // The slab's free list is a modification of Treiber's lock-free stack,
-        // using slab indices instead of pointers, and with a provision for
-        // growing the slab when needed.
+        // Most of the time, we're the only thread allocating into our
+        // shard (threads are spread round-robin across shards, one per
+        // CPU), so allocation rarely contends with another thread's
+        // allocation. The free-list CAS below still makes this correct if
+        // another thread sharing our shard (or concurrently removing a
+        // span from it) races us.
+        let shard_idx = self.current_shard();
+        let shard = &self.shards[shard_idx];
+
+        // The shard's free list is a modification of Treiber's lock-free
+        // stack, using slab indices instead of pointers. Unlike a flat
+        // `Vec`, growing the slab (allocating the page that a free-list slot
+        // lives on, if it hasn't been allocated yet) never requires
+        // exclusive access to the whole slab --- only a short-lived lock on
+        // that one page --- so it can never block a reader of any other
+        // page.
         //
         // In order to insert a new span into the slab, we "pop" the next free
         // index from the stack.
+        let mut backoff = Backoff::new();
         loop {
             // Acquire a snapshot of the head of the free list.
-            let head = self.next.load(Ordering::Relaxed);
-
-            {
-                // Try to insert the span without modifying the overall
-                // structure of the stack.
-                let this = self.inner.read();
-
-                // Can we insert without reallocating?
-                if head < this.slab.len() {
-                    // If someone else is writing to the head slot, we need to
-                    // acquire a new snapshot!
-                    if let Some(mut slot) = this.slab[head].try_write() {
-                        // Is the slot we locked actually empty? If not, fall
-                        // through and try to grow the slab.
-                        if let Some(next) = slot.next() {
-                            // Is our snapshot still valid?
-                            if self.next.compare_and_swap(head, next, Ordering::Release) == head {
-                                // We can finally fill the slot!
-                                slot.fill(span.take().unwrap(), attrs, new_visitor);
-                                return idx_to_id(head);
-                            }
-                        }
-                    }
-
-                    // Our snapshot got stale, try again!
-                    atomic::spin_loop_hint();
-                    continue;
-                }
-            }
-
-            // We need to grow the slab, and must acquire a write lock.
-            if let Some(mut this) = self.inner.try_write() {
-                let len = this.slab.len();
+            let head = shard.next.load(Ordering::Relaxed);
 
-                // Insert the span into a new slot.
-                let slot = Slot::new(span.take().unwrap(), attrs, new_visitor);
-                this.slab.push(RwLock::new(slot));
-                // TODO: can we grow the slab in chunks to avoid having to
-                // realloc as often?
+            let this = shard.inner.read();
+            let (page, offset) = page_of(head);
+            // Once every slot on every page is in use, the free list's head
+            // points one past the last slot of the last page; that page
+            // doesn't exist, the slab can't grow any further, and there's
+            // nowhere left to insert this span.
+            let slots = this.get_or_alloc_page(page)?;
 
-                // Update the head pointer and return.
-                self.next.store(len + 1, Ordering::Release);
-                return idx_to_id(len);
+            // If someone else is writing to the head slot, we need to
+            // acquire a new snapshot!
+            if let Some(mut slot) = slots[offset].try_write() {
+                // We got an uncontended snapshot of the head slot; any
+                // escalated spin count was for a different bout of
+                // contention that's already resolved, so start over.
+                backoff.reset();
+                // Is the slot we locked actually empty? If not, fall through
+                // and retry.
+                if let Some(next) = slot.next() {
+                    // Is our snapshot still valid?
+                    if shard.next.compare_and_swap(head, next, Ordering::Release) == head {
+                        // We can finally fill the slot!
+                        let generation = slot.generation;
+                        slot.fill(span.take().unwrap(), attrs, new_visitor);
+                        return Some(idx_to_id(shard_idx, head, generation));
+                    }
+                }
             }
 
-            atomic::spin_loop_hint();
+            // Our snapshot got stale, try again!
+            backoff.spin();
         }
     }
 
     /// Returns a `Span` to the span with the specified `id`, if one
     /// currently exists.
     #[inline]
-    pub(crate) fn get(&self, id: &Id) -> Option<Span<'_>> {
-        let lock = OwningHandle::try_new(self.inner.read(), |slab| {
-            unsafe { &*slab }.read_slot(id_to_idx(id)).ok_or(())
+    pub(crate) fn get(&self, id: &Id) -> Option<Span<'_, B>> {
+        let (shard_idx, idx, generation) = id_to_idx(id);
+        let shard = self.shards.get(shard_idx)?;
+        let ptr = shard.inner.read().slot(idx)?;
+        {
+            // Only the instant it takes to validate the slot and register
+            // our view is spent holding the slot's lock; the `Span` we
+            // return reads through `ptr` directly afterwards.
+            let slot = unsafe { &*ptr }.read();
+            if slot.generation != generation || slot.is_marked() {
+                return None;
+            }
+            match slot.span {
+                State::Full(_) => {}
+                State::Empty(_) => return None,
+            }
+            slot.acquire_ref();
+        }
+        Some(Span {
+            source: shard,
+            ptr,
+            idx,
+            generation,
         })
-        .ok()?;
-        Some(Span { lock })
     }
 
     /// Records that the span with the given `id` has the given `fields`.
@@ -355,9 +726,15 @@ impl Store {
     pub(crate) fn record<N>(&self, id: &Id, fields: &Record<'_>, new_recorder: &N)
     where
         N: for<'a> super::NewVisitor<'a>,
+        B: fmt::Write + AsRef<str>,
     {
-        let slab = self.inner.read();
-        let slot = slab.write_slot(id_to_idx(id));
+        let (shard, idx, generation) = id_to_idx(id);
+        let shard = match self.shards.get(shard) {
+            Some(shard) => shard,
+            None => return,
+        };
+        let slab = shard.inner.read();
+        let slot = slab.write_slot(idx, generation);
         if let Some(mut slot) = slot {
             slot.record(fields, new_recorder);
         }
@@ -367,14 +744,25 @@ impl Store {
     /// removes the span if it is zero.
     ///
     /// The allocated span slot will be reused when a new span is created.
-    pub(crate) fn drop_span(&self, id: Id) -> bool {
-        let this = self.inner.read();
-        let idx = id_to_idx(&id);
+    pub(crate) fn drop_span(&self, id: Id) -> bool
+    where
+        B: Clear,
+    {
+        let (shard_idx, idx, generation) = id_to_idx(&id);
+        let shard = match self.shards.get(shard_idx) {
+            Some(shard) => shard,
+            None => {
+                debug_panic!("tried to drop {:?} but it no longer exists!", id);
+                return false;
+            }
+        };
+        let this = shard.inner.read();
 
         if !this
-            .slab
-            .get(idx)
-            .map(|span| span.read().drop_ref())
+            .slot(idx)
+            .map(|slot| unsafe { &*slot }.read())
+            .filter(|slot| slot.generation == generation)
+            .map(|slot| slot.drop_ref())
             .unwrap_or_else(|| {
                 debug_panic!("tried to drop {:?} but it no longer exists!", id);
                 false
@@ -387,16 +775,34 @@ impl Store {
         // from std::Arc);
         atomic::fence(Ordering::Acquire);
 
-        this.remove(&self.next, idx);
+        // The span itself has closed, but any `Span` views still held into
+        // this slot must be dropped before it can actually be reclaimed;
+        // mark it closed and let `remove` decide (it re-checks under the
+        // slot's write lock, so this is safe to call unconditionally even
+        // if views are still outstanding).
+        if let Some(ptr) = this.slot(idx) {
+            unsafe { &*ptr }.read().mark_closed();
+        }
+        this.remove(&shard.next, idx);
         true
     }
 
     pub(crate) fn clone_span(&self, id: &Id) -> Id {
-        let this = self.inner.read();
-        let idx = id_to_idx(id);
-
-        if let Some(span) = this.slab.get(idx).map(|span| span.read()) {
-            span.clone_ref();
+        let (shard_idx, idx, generation) = id_to_idx(id);
+        if let Some(shard) = self.shards.get(shard_idx) {
+            let this = shard.inner.read();
+            let span = this
+                .slot(idx)
+                .map(|slot| unsafe { &*slot }.read())
+                .filter(|slot| slot.generation == generation);
+            if let Some(span) = span {
+                span.clone_ref();
+            } else {
+                debug_panic!(
+                    "tried to clone {:?}, but no span exists with that ID. this is a bug!",
+                    id
+                );
+            }
         } else {
             debug_panic!(
                 "tried to clone {:?}, but no span exists with that ID. this is a bug!",
@@ -405,14 +811,92 @@ impl Store {
         }
         id.clone()
     }
+
+    /// Returns a `Span` view of every span currently open anywhere in the
+    /// store, across every shard, in no particular order.
+    ///
+    /// Unlike `current`, which only looks at the calling thread's active
+    /// span stack, this walks every slot on every shard, so it's the basis
+    /// for a "dump all open spans" diagnostic rather than anything on the
+    /// span-creation hot path.
+    ///
+    /// Each shard's slab is read-locked only long enough to snapshot its
+    /// slot pointers, and each slot's own lock is taken (and released)
+    /// individually to check whether it's actually occupied --- this never
+    /// holds more than one page or slot lock at a time (besides the
+    /// shard's slab read lock while snapshotting), so it can't deadlock against a
+    /// concurrent `new_span` or `drop_span` on another shard or slot.
+    pub(crate) fn unique_iter(&self) -> impl Iterator<Item = Span<'_, B>> + '_
+    where
+        B: Clear,
+    {
+        self.shards.iter().flat_map(move |shard| {
+            let slots = shard.inner.read().slots();
+            slots.into_iter().filter_map(move |(idx, ptr)| {
+                let generation = {
+                    let slot = unsafe { &*ptr }.read();
+                    if slot.is_marked() {
+                        return None;
+                    }
+                    match slot.span {
+                        State::Full(_) => {}
+                        State::Empty(_) => return None,
+                    }
+                    slot.acquire_ref();
+                    slot.generation
+                };
+                Some(Span {
+                    source: shard,
+                    ptr,
+                    idx,
+                    generation,
+                })
+            })
+        })
+    }
+}
+
+impl<B> SpanStore<B> for Store<B> {
+    type Source = Shard<B>;
+
+    fn get(&self, id: &Id) -> Option<Span<'_, B, Shard<B>>> {
+        Store::get(self, id)
+    }
+
+    fn record<N>(&self, id: &Id, fields: &Record<'_>, new_recorder: &N)
+    where
+        N: for<'a> super::NewVisitor<'a>,
+        B: fmt::Write + AsRef<str>,
+    {
+        Store::record(self, id, fields, new_recorder)
+    }
+
+    fn drop_span(&self, id: Id) -> bool
+    where
+        B: Clear,
+    {
+        Store::drop_span(self, id)
+    }
+
+    fn clone_span(&self, id: &Id) -> Id {
+        Store::clone_span(self, id)
+    }
 }
 
 impl Data {
-    pub(crate) fn new(attrs: &Attributes<'_>, store: &Store) -> Self {
+    /// Builds a new span's data, resolving its parent against whatever kind
+    /// of `store` (growable or fixed-capacity) it's being created in.
+    pub(crate) fn new<B, S>(attrs: &Attributes<'_>, store: &S) -> Self
+    where
+        S: SpanStore<B>,
+    {
         let parent = if attrs.is_root() {
             None
         } else if attrs.is_contextual() {
-            store.current()
+            CONTEXT
+                .try_with(|current| current.borrow().last().map(|span| store.clone_span(span)))
+                .ok()
+                .flatten()
         } else {
             attrs.parent().map(|id| store.clone_span(id))
         };
@@ -441,22 +925,18 @@ impl Drop for Data {
     }
 }
 
-impl Slot {
-    fn new<N>(mut data: Data, attrs: &Attributes<'_>, new_visitor: &N) -> Self
+impl<B> Slot<B> {
+    /// Creates a new, empty slot whose free-list pointer is `next`, as part
+    /// of allocating a fresh page.
+    fn new_empty(next: usize) -> Self
     where
-        N: for<'a> super::NewVisitor<'a>,
+        B: Default,
     {
-        let mut fields = String::new();
-        {
-            let mut recorder = new_visitor.make(&mut fields, true);
-            attrs.record(&mut recorder);
-        }
-        if fields.is_empty() {
-            data.is_empty = false;
-        }
         Self {
-            fields,
-            span: State::Full(data),
+            fields: B::default(),
+            generation: 0,
+            lifecycle: AtomicUsize::new(0),
+            span: State::Empty(next),
         }
     }
 
@@ -470,13 +950,14 @@ impl Slot {
     fn fill<N>(&mut self, mut data: Data, attrs: &Attributes<'_>, new_visitor: &N) -> usize
     where
         N: for<'a> super::NewVisitor<'a>,
+        B: fmt::Write + AsRef<str>,
     {
         let fields = &mut self.fields;
         {
             let mut recorder = new_visitor.make(fields, true);
             attrs.record(&mut recorder);
         }
-        if fields.is_empty() {
+        if fields.as_ref().is_empty() {
             data.is_empty = false;
         }
         match mem::replace(&mut self.span, State::Full(data)) {
@@ -488,6 +969,7 @@ impl Slot {
     fn record<N>(&mut self, fields: &Record<'_>, new_visitor: &N)
     where
         N: for<'a> super::NewVisitor<'a>,
+        B: fmt::Write + AsRef<str>,
     {
         let state = &mut self.span;
         let buf = &mut self.fields;
@@ -498,7 +980,7 @@ impl Slot {
                     let mut recorder = new_visitor.make(buf, data.is_empty);
                     fields.record(&mut recorder);
                 }
-                if buf.is_empty() {
+                if buf.as_ref().is_empty() {
                     data.is_empty = false;
                 }
             }
@@ -522,54 +1004,666 @@ impl Slot {
             }
         }
     }
+
+    /// Registers a new `Span` view into this slot.
+    fn acquire_ref(&self) {
+        self.lifecycle.fetch_add(REF_ONE, Ordering::Relaxed);
+    }
+
+    /// Releases a `Span` view into this slot. Returns `true` if the
+    /// observed count of remaining views, post-release, looks like it may
+    /// have reached zero --- a hint that reclamation might now be due, to
+    /// be confirmed by `Slab::remove` under the slot's write lock.
+    fn release_ref(&self) -> bool {
+        let prev = self.lifecycle.fetch_sub(REF_ONE, Ordering::Release);
+        (prev - REF_ONE) >> 1 == 0
+    }
+
+    /// Marks this slot's span as closed (its last `Id` has been dropped).
+    fn mark_closed(&self) {
+        self.lifecycle.fetch_or(MARKED, Ordering::Release);
+    }
+
+    /// A slot may be reclaimed once its span has closed and no `Span` views
+    /// into it remain.
+    fn is_reclaimable(&self) -> bool {
+        let lifecycle = self.lifecycle.load(Ordering::Acquire);
+        lifecycle & MARKED == MARKED && lifecycle >> 1 == 0
+    }
+
+    fn is_marked(&self) -> bool {
+        self.lifecycle.load(Ordering::Acquire) & MARKED == MARKED
+    }
+}
+
+impl<B> Slab<B> {
+    /// Returns the page at `page`, allocating it first if it doesn't exist
+    /// yet, or `None` if `page` is beyond `PAGE_COUNT` --- i.e. the slab has
+    /// hit its hard capacity (`INITIAL_PAGE_SIZE * (2^PAGE_COUNT - 1)`
+    /// slots) and cannot grow any further.
+    ///
+    /// The returned reference is valid for as long as `self` is: once a page
+    /// is allocated it is never moved or freed, so letting go of the lock
+    /// used to check/perform the allocation doesn't invalidate slots handed
+    /// out from it.
+    fn get_or_alloc_page(&self, page: usize) -> Option<&[RwLock<Slot<B>>]>
+    where
+        B: Default,
+    {
+        let slots = &self.pages.get(page)?.slots;
+
+        if let Some(slots) = slots.read().as_ref() {
+            // Safety: see the comment on the struct; the box is never moved
+            // or dropped again once it's `Some`, so a pointer into it
+            // remains valid after we drop the lock guard that produced it.
+            return Some(unsafe { &*(&**slots as *const [RwLock<Slot<B>>]) });
+        }
+
+        let mut guard = slots.write();
+        if guard.is_none() {
+            let start = page_start(page);
+            let len = page_len(page);
+            *guard = Some(
+                (0..len)
+                    .map(|i| RwLock::new(Slot::new_empty(start + i + 1)))
+                    .collect(),
+            );
+        }
+        let slots = guard.as_ref().expect("just initialized above");
+        Some(unsafe { &*(&**slots as *const [RwLock<Slot<B>>]) })
+    }
+
+    /// Returns a pointer to the slot at `idx`, if the page it lives on has
+    /// been allocated.
+    fn slot(&self, idx: usize) -> Option<*const RwLock<Slot<B>>> {
+        let (page, offset) = page_of(idx);
+        let slots = self.pages.get(page)?.slots.read();
+        let ptr = slots.as_ref()?.get(offset)? as *const RwLock<Slot<B>>;
+        Some(ptr)
+    }
+
+    /// Returns the global index and pointer of every slot on every
+    /// allocated page, in ascending index order.
+    ///
+    /// This never allocates a page that doesn't already exist --- a page
+    /// that hasn't been reached yet simply contributes no slots --- and
+    /// only ever holds one page's lock at a time, for the instant it takes
+    /// to snapshot the page's slot pointers, so it can't deadlock against a
+    /// concurrent `get_or_alloc_page`.
+    fn slots(&self) -> Vec<(usize, *const RwLock<Slot<B>>)> {
+        self.pages
+            .iter()
+            .enumerate()
+            .flat_map(|(page, p)| {
+                let start = page_start(page);
+                let guard = p.slots.read();
+                let slots: &[RwLock<Slot<B>>] = match guard.as_ref() {
+                    Some(slots) => slots,
+                    None => &[],
+                };
+                slots
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, slot)| (start + offset, slot as *const RwLock<Slot<B>>))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[inline]
+    fn write_slot(&self, idx: usize, generation: usize) -> Option<RwLockWriteGuard<'_, Slot<B>>> {
+        let slot = unsafe { &*self.slot(idx)? }.write();
+        if slot.generation == generation {
+            Some(slot)
+        } else {
+            None
+        }
+    }
+
+    /// Reclaims the slot at `idx` onto the free list, if it is actually
+    /// eligible: its span must have closed and no `Span` views into it may
+    /// remain.
+    ///
+    /// This is always safe to call speculatively --- both `Store::drop_span`
+    /// (once a span closes) and the last `Span` to drop call it, and
+    /// whichever wins the race performs the reclamation --- because
+    /// eligibility is re-checked here, under the slot's write lock, rather
+    /// than trusted from an earlier, potentially stale, observation.
+    fn remove(&self, next: &AtomicUsize, idx: usize) -> Option<Data>
+    where
+        B: Clear,
+    {
+        reclaim_slot(self.slot(idx)?, next, idx)
+    }
+}
+
+impl<B> SlotSource<B> for Shard<B> {
+    fn reclaim(&self, idx: usize) -> Option<Data>
+    where
+        B: Clear,
+    {
+        self.inner.read().remove(&self.next, idx)
+    }
+}
+
+/// The free-list reclamation dance shared by every kind of slot source
+/// (`Slab::remove`, `StaticShard::reclaim`): empties the slot behind `ptr`
+/// and pushes `idx` onto the head of the free list rooted at `next`, but
+/// only if the slot is actually reclaimable (see `Slot::is_reclaimable`);
+/// this is essentially a variant of Treiber's stack algorithm, using slab
+/// indices in place of pointers.
+///
+/// Unlike the allocation loops in `Store`/`StaticStore::new_span`, this
+/// loop's only lock acquisition (`ptr`'s write lock) always blocks rather
+/// than failing outright, so there's no uncontended-snapshot moment to
+/// reset `backoff` on; its spin count only ever escalates on a genuine
+/// stale-CAS retry here, which is exactly what it should do.
+fn reclaim_slot<B>(ptr: *const RwLock<Slot<B>>, next: &AtomicUsize, idx: usize) -> Option<Data>
+where
+    B: Clear,
+{
+    let mut backoff = Backoff::new();
+    loop {
+        // Get a snapshot of the current free-list head.
+        let head = next.load(Ordering::Relaxed);
+
+        // Empty the data stored at that slot.
+        let mut slot = unsafe { &*ptr }.write();
+        if !slot.is_reclaimable() {
+            return None;
+        }
+        let data = match mem::replace(&mut slot.span, State::Empty(head)) {
+            State::Full(data) => data,
+            state => {
+                // The slot has already been emptied; leave
+                // everything as it was and return `None`!
+                slot.span = state;
+                return None;
+            }
+        };
+
+        // Is our snapshot still valid?
+        if next.compare_and_swap(head, idx, Ordering::Release) == head {
+            // Reset the buffer but retain its allocated capacity for
+            // future spans.
+            Clear::clear(&mut slot.fields);
+            // Bump the generation so that any `Id` pointing at the old
+            // occupant of this slot is rejected once the slot is reused.
+            slot.generation = (slot.generation + 1) & MAX_GENERATION;
+            // Reset the lifecycle for the slot's next occupant.
+            slot.lifecycle.store(0, Ordering::Release);
+            return Some(data);
+        }
+
+        backoff.spin();
+    }
+}
+
+/// A fixed-capacity, heap-allocation-free string buffer.
+///
+/// This is the `StaticStore`'s counterpart to `String` as a slot's field
+/// buffer: writes go directly into an inline `[u8; N]` rather than a heap
+/// allocation, so a subscriber built on `StaticStore` never allocates on the
+/// span hot path. A write that would overflow the buffer's capacity is
+/// truncated rather than growing it or panicking, and --- per
+/// `fmt::Write`'s contract --- reported back as an `Err` so callers know
+/// the field was cut short rather than recorded in full.
+#[derive(Debug, Clone)]
+pub(crate) struct ArrayString<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Default for ArrayString<N> {
+    fn default() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+}
+
+impl<const N: usize> Clear for ArrayString<N> {
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> AsRef<str> for ArrayString<N> {
+    fn as_ref(&self) -> &str {
+        // Safety: every byte in `buf[..len]` was written by `write_str`
+        // below, which only ever appends bytes from a `&str` up to the
+        // nearest preceding UTF-8 character boundary, so this range is
+        // always valid UTF-8.
+        unsafe { str::from_utf8_unchecked(&self.buf[..self.len]) }
+    }
+}
+
+impl<const N: usize> fmt::Write for ArrayString<N> {
+    /// Appends as much of `s` as fits in the remaining capacity.
+    ///
+    /// This is the one place where an `ArrayString` is lossy: once it's
+    /// full, later writes are truncated rather than growing the buffer.
+    /// `fmt::Write`'s contract requires that a `write_str` which doesn't
+    /// write the whole string return `Err`, so that `write!`-based visitors
+    /// see their output was truncated instead of believing a field was
+    /// recorded in full.
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let remaining = N - self.len;
+        let mut to_write = s.len().min(remaining);
+        // Truncating at an arbitrary byte offset could split a multi-byte
+        // character in half, so walk back to the nearest character
+        // boundary at or before the truncation point.
+        while to_write > 0 && !s.is_char_boundary(to_write) {
+            to_write -= 1;
+        }
+        self.buf[self.len..self.len + to_write].copy_from_slice(&s.as_bytes()[..to_write]);
+        self.len += to_write;
+        if to_write < s.len() {
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+/// The fixed-capacity counterpart to [`Shard`]: rather than a page table
+/// that grows on demand, an inline array of exactly `N` slots with its own
+/// free list. Used by [`StaticStore`], which has exactly one shard (there's
+/// no point sharding a store that can't grow to begin with).
+struct StaticShard<const N: usize, B = ArrayString<64>> {
+    slots: [RwLock<Slot<B>>; N],
+    next: AtomicUsize,
+}
+
+impl<const N: usize, B> fmt::Debug for StaticShard<N, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StaticShard")
+            .field("capacity", &N)
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+impl<const N: usize, B> StaticShard<N, B> {
+    fn new() -> Self
+    where
+        B: Default,
+    {
+        Self {
+            slots: std::array::from_fn(|i| RwLock::new(Slot::new_empty(i + 1))),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    fn slot(&self, idx: usize) -> Option<*const RwLock<Slot<B>>> {
+        Some(self.slots.get(idx)? as *const RwLock<Slot<B>>)
+    }
 }
 
-impl Slab {
+impl<const N: usize, B> SlotSource<B> for StaticShard<N, B> {
+    fn reclaim(&self, idx: usize) -> Option<Data>
+    where
+        B: Clear,
+    {
+        reclaim_slot(self.slot(idx)?, &self.next, idx)
+    }
+}
+
+/// A fixed-capacity, allocation-free counterpart to [`Store`], for
+/// subscribers that must run with a bounded, preallocated span budget and
+/// no dynamic allocation on the span hot path (e.g. embedded targets with no
+/// global allocator).
+///
+/// Unlike `Store`, a `StaticStore` never grows past its `N` slots and is not
+/// sharded across threads: `new_span` simply returns `None` once the free
+/// list is exhausted, rather than allocating more room.
+pub(crate) struct StaticStore<const N: usize, B = ArrayString<64>> {
+    shard: StaticShard<N, B>,
+}
+
+impl<const N: usize, B> fmt::Debug for StaticStore<N, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StaticStore").field("capacity", &N).finish()
+    }
+}
+
+impl<const N: usize, B> StaticStore<N, B> {
+    pub(crate) fn new() -> Self
+    where
+        B: Default,
+    {
+        Self {
+            shard: StaticShard::new(),
+        }
+    }
+
+    /// Inserts a new span with the given data and fields into the store,
+    /// returning an ID for that span, or `None` if the store's fixed
+    /// capacity has been exhausted.
     #[inline]
-    fn write_slot(&self, idx: usize) -> Option<RwLockWriteGuard<'_, Slot>> {
-        self.slab.get(idx).map(RwLock::write)
+    pub(crate) fn new_span<N2>(&self, attrs: &Attributes<'_>, new_visitor: &N2) -> Option<Id>
+    where
+        N2: for<'a> super::NewVisitor<'a>,
+        B: Default + fmt::Write + AsRef<str>,
+    {
+        let mut span = Some(Data::new(attrs, self));
+
+        let mut backoff = Backoff::new();
+        loop {
+            let head = self.shard.next.load(Ordering::Relaxed);
+            // The free list is only `N` slots deep; once its head points
+            // past the end of the array, every slot is occupied and there's
+            // nowhere to grow into, unlike the heap-backed `Store`.
+            let slot = self.shard.slots.get(head)?;
+
+            if let Some(mut slot) = slot.try_write() {
+                // We got an uncontended snapshot of the head slot; any
+                // escalated spin count was for a different bout of
+                // contention that's already resolved, so start over.
+                backoff.reset();
+                if let Some(next) = slot.next() {
+                    if self
+                        .shard
+                        .next
+                        .compare_and_swap(head, next, Ordering::Release)
+                        == head
+                    {
+                        let generation = slot.generation;
+                        slot.fill(span.take().unwrap(), attrs, new_visitor);
+                        return Some(idx_to_id(0, head, generation));
+                    }
+                }
+            }
+
+            backoff.spin();
+        }
     }
 
+    /// Returns a `Span` to the span with the specified `id`, if one
+    /// currently exists.
     #[inline]
-    fn read_slot(&self, idx: usize) -> Option<RwLockReadGuard<'_, Slot>> {
-        self.slab
-            .get(idx)
-            .map(RwLock::read)
-            .and_then(|lock| match lock.span {
-                State::Empty(_) => None,
-                State::Full(_) => Some(lock),
+    pub(crate) fn get(&self, id: &Id) -> Option<Span<'_, B, StaticShard<N, B>>> {
+        let (_, idx, generation) = id_to_idx(id);
+        let ptr = self.shard.slot(idx)?;
+        {
+            let slot = unsafe { &*ptr }.read();
+            if slot.generation != generation || slot.is_marked() {
+                return None;
+            }
+            match slot.span {
+                State::Full(_) => {}
+                State::Empty(_) => return None,
+            }
+            slot.acquire_ref();
+        }
+        Some(Span {
+            source: &self.shard,
+            ptr,
+            idx,
+            generation,
+        })
+    }
+
+    /// Records that the span with the given `id` has the given `fields`.
+    #[inline]
+    pub(crate) fn record<N2>(&self, id: &Id, fields: &Record<'_>, new_recorder: &N2)
+    where
+        N2: for<'a> super::NewVisitor<'a>,
+        B: fmt::Write + AsRef<str>,
+    {
+        let (_, idx, generation) = id_to_idx(id);
+        if let Some(ptr) = self.shard.slot(idx) {
+            let mut slot = unsafe { &*ptr }.write();
+            if slot.generation == generation {
+                slot.record(fields, new_recorder);
+            }
+        }
+    }
+
+    /// Decrements the reference count of the span with the given `id`, and
+    /// removes the span if it is zero.
+    pub(crate) fn drop_span(&self, id: Id) -> bool
+    where
+        B: Clear,
+    {
+        let (_, idx, generation) = id_to_idx(&id);
+        let ptr = match self.shard.slot(idx) {
+            Some(ptr) => ptr,
+            None => {
+                debug_panic!("tried to drop {:?} but it no longer exists!", id);
+                return false;
+            }
+        };
+
+        if !Some(unsafe { &*ptr }.read())
+            .filter(|slot| slot.generation == generation)
+            .map(|slot| slot.drop_ref())
+            .unwrap_or_else(|| {
+                debug_panic!("tried to drop {:?} but it no longer exists!", id);
+                false
             })
+        {
+            return false;
+        }
+
+        atomic::fence(Ordering::Acquire);
+        unsafe { &*ptr }.read().mark_closed();
+        reclaim_slot(ptr, &self.shard.next, idx);
+        true
     }
 
-    /// Remove a span slot from the slab.
-    fn remove(&self, next: &AtomicUsize, idx: usize) -> Option<Data> {
-        // Again we are essentially implementing a variant of Treiber's stack
-        // algorithm to push the removed span's index into the free list.
-        loop {
-            // Get a snapshot of the current free-list head.
-            let head = next.load(Ordering::Relaxed);
-
-            // Empty the data stored at that slot.
-            let mut slot = self.slab[idx].write();
-            let data = match mem::replace(&mut slot.span, State::Empty(head)) {
-                State::Full(data) => data,
-                state => {
-                    // The slot has already been emptied; leave
-                    // everything as it was and return `None`!
-                    slot.span = state;
-                    return None;
+    pub(crate) fn clone_span(&self, id: &Id) -> Id {
+        let (_, idx, generation) = id_to_idx(id);
+        let cloned = self
+            .shard
+            .slot(idx)
+            .map(|ptr| unsafe { &*ptr }.read())
+            .and_then(|slot| {
+                if slot.generation == generation {
+                    slot.clone_ref();
+                    Some(())
+                } else {
+                    None
                 }
-            };
-
-            // Is our snapshot still valid?
-            if next.compare_and_swap(head, idx, Ordering::Release) == head {
-                // Empty the string but retain the allocated capacity
-                // for future spans.
-                slot.fields.clear();
-                return Some(data);
+            });
+        if cloned.is_none() {
+            debug_panic!(
+                "tried to clone {:?}, but no span exists with that ID. this is a bug!",
+                id
+            );
+        }
+        id.clone()
+    }
+}
+
+impl<const N: usize, B> SpanStore<B> for StaticStore<N, B> {
+    type Source = StaticShard<N, B>;
+
+    fn get(&self, id: &Id) -> Option<Span<'_, B, StaticShard<N, B>>> {
+        StaticStore::get(self, id)
+    }
+
+    fn record<N2>(&self, id: &Id, fields: &Record<'_>, new_recorder: &N2)
+    where
+        N2: for<'a> super::NewVisitor<'a>,
+        B: fmt::Write + AsRef<str>,
+    {
+        StaticStore::record(self, id, fields, new_recorder)
+    }
+
+    fn drop_span(&self, id: Id) -> bool
+    where
+        B: Clear,
+    {
+        StaticStore::drop_span(self, id)
+    }
+
+    fn clone_span(&self, id: &Id) -> Id {
+        StaticStore::clone_span(self, id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_core::{field::FieldSet, metadata::Kind, Metadata};
+
+    // A minimal `Callsite` so we have something to hang a `&'static
+    // Metadata<'static>` off of. Its contents are never inspected by the
+    // tests below; it only needs to exist so that `Data` (which stores a
+    // `&'static Metadata<'static>`) can be built by hand without going
+    // through `Store::new_span` --- which requires a real `NewVisitor`
+    // impl from the subscriber this module doesn't have access to.
+    struct TestCallsite;
+
+    impl tracing_core::callsite::Callsite for TestCallsite {
+        fn set_interest(&self, _interest: tracing_core::subscriber::Interest) {}
+
+        fn metadata(&self) -> &Metadata<'_> {
+            &TEST_METADATA
+        }
+    }
+
+    static TEST_CALLSITE: TestCallsite = TestCallsite;
+
+    static TEST_METADATA: Metadata<'static> = Metadata::new(
+        "test_span",
+        module_path!(),
+        tracing_core::Level::TRACE,
+        None,
+        None,
+        None,
+        FieldSet::new(&[], tracing_core::identify_callsite!(&TEST_CALLSITE)),
+        Kind::SPAN,
+    );
+
+    /// Plants a span directly into shard 0's slot 0, bypassing
+    /// `Store::new_span` (and the `NewVisitor` it requires), and returns
+    /// the `Id` for it.
+    fn plant(store: &Store<String>) -> Id {
+        let shard = &store.shards[0];
+        let slab = shard.inner.read();
+        let ptr = slab.slot(0).expect("slot 0 should exist after with_capacity(1)");
+        let mut slot = unsafe { &*ptr }.write();
+        slot.span = State::Full(Data {
+            parent: None,
+            metadata: &TEST_METADATA,
+            ref_count: AtomicUsize::new(1),
+            is_empty: true,
+        });
+        idx_to_id(0, 0, slot.generation)
+    }
+
+    #[test]
+    fn stale_id_is_rejected_after_recycle() {
+        let store: Store<String> = Store::with_capacity(1);
+        let id = plant(&store);
+        assert!(store.get(&id).is_some(), "freshly planted span should resolve");
+
+        assert!(store.drop_span(id.clone()), "dropping the only Id should close the span");
+
+        // The slot has been recycled (and its generation bumped) by now;
+        // the old `Id` must never be mistaken for whatever span --- if
+        // any --- now occupies the slot.
+        assert!(
+            store.get(&id).is_none(),
+            "a stale Id from a recycled slot must not resolve"
+        );
+    }
+
+    #[test]
+    fn reclaim_is_deferred_past_an_outstanding_span() {
+        let store: Store<String> = Store::with_capacity(1);
+        let id = plant(&store);
+
+        // Take a `Span` view before the span closes; per the slot's
+        // `Present` -> `Marked` -> `Removing` lifecycle, it must keep the
+        // slot from being reclaimed until this view (not just the `Id`) is
+        // dropped.
+        let view = store.get(&id).expect("freshly planted span should resolve");
+
+        assert!(store.drop_span(id.clone()), "dropping the only Id should close the span");
+
+        let generation_while_held = unsafe { &*store.shards[0].inner.read().slot(0).unwrap() }
+            .read()
+            .generation;
+        assert_eq!(
+            generation_while_held, 0,
+            "the slot must not be reclaimed while a Span view is still outstanding"
+        );
+
+        drop(view);
+
+        let generation_after_drop = unsafe { &*store.shards[0].inner.read().slot(0).unwrap() }
+            .read()
+            .generation;
+        assert_eq!(
+            generation_after_drop, 1,
+            "dropping the last outstanding Span view should finally reclaim the slot"
+        );
+    }
+
+    #[test]
+    fn page_of_finds_the_right_page_at_every_boundary() {
+        // Index 0 must land on page 0, regardless of `usize`'s width.
+        assert_eq!(page_of(0), (0, 0));
+        assert_eq!(page_of(INITIAL_PAGE_SIZE - 1), (0, INITIAL_PAGE_SIZE - 1));
+
+        // The last index of a page must resolve to that page, and the
+        // following index --- the first of the next, doubled-size page ---
+        // must roll over to offset 0 on it.
+        for page in 0..PAGE_COUNT {
+            let start = page_start(page);
+            let last = start + page_len(page) - 1;
+            assert_eq!(page_of(last), (page, page_len(page) - 1));
+            if page + 1 < PAGE_COUNT {
+                assert_eq!(page_of(last + 1), (page + 1, 0));
             }
+        }
+    }
+
+    mod array_string {
+        use super::*;
+        use fmt::Write;
+
+        #[test]
+        fn write_that_exactly_fills_succeeds() {
+            let mut s: ArrayString<5> = ArrayString::default();
+            assert!(s.write_str("abcde").is_ok());
+            assert_eq!(s.as_ref(), "abcde");
+        }
+
+        #[test]
+        fn write_into_a_full_buffer_is_truncated_and_errs() {
+            let mut s: ArrayString<5> = ArrayString::default();
+            assert!(s.write_str("abcde").is_ok());
+
+            // `remaining == 0`: nothing more can fit, so nothing is
+            // appended, but the caller must be told the write was lossy.
+            assert!(s.write_str("f").is_err());
+            assert_eq!(s.as_ref(), "abcde");
+        }
+
+        #[test]
+        fn write_that_overflows_mid_char_truncates_to_the_prior_boundary() {
+            // "é" is two UTF-8 bytes; a 1-byte remaining capacity can't fit
+            // it, so the write must back off to the boundary before it
+            // rather than splitting the character in half.
+            let mut s: ArrayString<2> = ArrayString::default();
+            assert!(s.write_str("aé").is_err());
+            assert_eq!(s.as_ref(), "a");
+        }
 
-            atomic::spin_loop_hint();
+        #[test]
+        fn as_ref_is_always_valid_utf8_after_a_truncating_write() {
+            let mut s: ArrayString<4> = ArrayString::default();
+            // "日本" is six bytes, one more three-byte character than fits
+            // in a 4-byte buffer; the second character must be dropped
+            // entirely rather than leaving a partial, invalid byte sequence
+            // behind.
+            assert!(s.write_str("日本").is_err());
+            assert!(str::from_utf8(s.as_ref().as_bytes()).is_ok());
+            assert_eq!(s.as_ref(), "日");
         }
     }
 }